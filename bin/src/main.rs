@@ -50,7 +50,46 @@ fn main() {
         .enable_all()
         .build()
         .unwrap()
-        .block_on(_main(shutdown_tx, shutdown_rx))
+        .block_on(async {
+            let transforms = load_transforms();
+            tokio::spawn(spawn_system_monitor());
+            _main(shutdown_tx, shutdown_rx, transforms).await
+        })
+}
+
+/// Loads the WASM message rewrite modules configured via `MZ_WASM_MODULES`,
+/// if any. `_main`/`stream_adapter` apply these, in order, to each line
+/// before it's enqueued for ingest.
+fn load_transforms() -> Option<Arc<wasm::Transforms>> {
+    let dir = std::env::var_os(config::env_vars::WASM_MODULES).map(std::path::PathBuf::from)?;
+    let pool_size = std::env::var(config::env_vars::WASM_POOL_SIZE)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(4);
+
+    match wasm::Transforms::load(&dir, pool_size) {
+        Ok(transforms) => Some(transforms),
+        Err(e) => {
+            warn!("failed to load wasm transform modules from {:?}: {}", dir, e);
+            None
+        }
+    }
+}
+
+/// Periodically samples the agent's own resource usage (memory, cpu, open
+/// file descriptors, threads) and publishes it as gauges on the metrics
+/// endpoint. Also logs a structured summary line when
+/// `MZ_LOG_METRIC_SERVER_STATS` is set, so operators can alert on the agent
+/// approaching its fd ceiling.
+async fn spawn_system_monitor() {
+    let interval = std::env::var(config::env_vars::SYSTEM_MONITOR_INTERVAL)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(std::time::Duration::from_secs)
+        .unwrap_or(std::time::Duration::from_secs(30));
+    let log_summary = std::env::var_os(config::env_vars::LOG_METRIC_SERVER_STATS).is_some();
+
+    sysmon::SystemMonitor::new(interval, log_summary).run().await
 }
 
 #[cfg(target_os = "linux")]
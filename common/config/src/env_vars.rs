@@ -30,6 +30,20 @@ pub const INGEST_BUFFER_SIZE: &str = "MZ_INGEST_BUFFER_SIZE";
 pub const RETRY_DIR: &str = "MZ_RETRY_DIR";
 pub const RETRY_DISK_LIMIT: &str = "MZ_RETRY_DISK_LIMIT";
 pub const INTERNAL_FS_DELAY: &str = "MZ_INTERNAL_FS_DELAY";
+pub const WASM_MODULES: &str = "MZ_WASM_MODULES";
+pub const WASM_POOL_SIZE: &str = "MZ_WASM_POOL_SIZE";
+pub const SYSTEM_MONITOR_INTERVAL: &str = "MZ_SYSTEM_MONITOR_INTERVAL";
+pub const TLS_CA_CERTS: &str = "MZ_TLS_CA_CERTS";
+pub const TLS_USE_NATIVE_ROOTS: &str = "MZ_TLS_USE_NATIVE_ROOTS";
+pub const TLS_CLIENT_CERT: &str = "MZ_TLS_CLIENT_CERT";
+pub const TLS_CLIENT_KEY: &str = "MZ_TLS_CLIENT_KEY";
+pub const RETRY_MAX_DELAY: &str = "MZ_RETRY_MAX_DELAY";
+pub const RETRY_MAX_ATTEMPTS: &str = "MZ_RETRY_MAX_ATTEMPTS";
+pub const RETRY_THROTTLE: &str = "MZ_RETRY_THROTTLE";
+pub const RETRY_DEAD_LETTER_DIR: &str = "MZ_RETRY_DEAD_LETTER_DIR";
+pub const RATE_LIMIT_STARTING: &str = "MZ_RATE_LIMIT_STARTING";
+pub const RATE_LIMIT_MIN: &str = "MZ_RATE_LIMIT_MIN";
+pub const RATE_LIMIT_MAX: &str = "MZ_RATE_LIMIT_MAX";
 
 // unused or deprecated
 pub const INGESTION_KEY_ALTERNATE: &str = "LOGDNA_AGENT_KEY";
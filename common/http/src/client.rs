@@ -1,9 +1,11 @@
 use std::convert::TryInto;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use crate::limit::RateLimiter;
-use crate::retry::Retry;
+use crate::limit::{RateLimiter, RateLimiterConfig};
+use crate::retry::{Retry, RetryConfig};
+use crate::tls::TlsConfig;
 use crate::types::body::IngestBodyBuffer;
 use crate::types::client::Client as HttpClient;
 use crate::types::error::HttpError;
@@ -29,24 +31,32 @@ impl Client {
     pub fn new(
         template: RequestTemplate,
         state_handles: Option<(FileOffsetWriteHandle, FileOffsetFlushHandle)>,
-        retry_base_delay: Duration,
+        retry_config: RetryConfig,
+        retry_dir: PathBuf,
+        retry_dead_letter_dir: PathBuf,
+        tls: TlsConfig,
     ) -> Self {
         let (state_write, state_flush) = state_handles
             .map(|(sw, sf)| (Some(sw), Some(sf)))
             .unwrap_or((None, None));
+        // Build the real rustls config here, at startup, rather than handing
+        // the unvalidated TlsConfig down to the connector -- a bad CA bundle
+        // or client cert/key pair should fail fast now, not at first send.
+        let tls_config = tls
+            .client_config()
+            .expect("invalid TLS configuration for the ingest client");
         Self {
-            inner: HttpClient::new(template),
-            limiter: RateLimiter::new(10),
-            retry: Arc::new(Retry::new(retry_base_delay)),
+            inner: HttpClient::new(template, tls_config),
+            limiter: RateLimiter::with_config(RateLimiterConfig::from_env()),
+            retry: Arc::new(Retry::new(retry_config, retry_dir, retry_dead_letter_dir)),
             state_write,
             state_flush,
-            // retry_step_delay,
         }
     }
 
     pub async fn send(&self, body: IngestBodyBuffer, file_offsets: Option<&[Offset]>) {
         match self.retry.poll().await {
-            Ok((offsets, Some(body))) => {
+            Ok((offsets, Some((body, attempt)))) => {
                 if let (Some(sw), Some(offsets)) = (self.state_write.as_ref(), &offsets) {
                     for (file_name, offset) in offsets {
                         trace!("Updating offset for {:?} to {}", file_name, *offset);
@@ -55,7 +65,7 @@ impl Client {
                         };
                     }
                 }
-                self.make_request(body, offsets.as_deref()).await
+                self.make_request(body, offsets.as_deref(), attempt).await
             }
             Err(e) => error!("error polling retry: {}", e),
             _ => {}
@@ -71,6 +81,20 @@ impl Client {
                 }
             }
         }
+        self.make_request(body, file_offsets, 0).await
+    }
+
+    /// Sends `body` and classifies the response, requeuing it for retry (at
+    /// `attempt + 1`) on failure or timeout. `attempt` is how many times
+    /// this exact body has already been through this path before -- 0 for a
+    /// request seen for the first time, or whatever was persisted alongside
+    /// it if it came back out of [`Retry::poll`].
+    async fn make_request(
+        &self,
+        body: IngestBodyBuffer,
+        file_offsets: Option<&[Offset]>,
+        attempt: u32,
+    ) {
         let sf = self.state_flush.as_ref();
         let start = Instant::now();
         match self
@@ -78,21 +102,24 @@ impl Client {
             .send(self.limiter.get_slot(body).as_ref().clone())
             .await
         {
-            Ok(Response::Failed(_, s, r)) => {
+            Ok(Response::Failed(_, s, r, retry_after)) => {
                 Metrics::http().add_request_failure(start);
                 warn!("bad response {}: {}", s, r);
+                if s == 429 || s == 503 {
+                    self.limiter.on_throttled(retry_after).await;
+                }
             }
             Err(HttpError::Send(body, e)) => {
                 Metrics::http().add_request_failure(start);
                 warn!("failed sending http request, retrying: {}", e);
-                if let Err(e) = retry.retry(file_offsets, &body) {
+                if let Err(e) = self.retry.retry_attempt(file_offsets, &body, attempt + 1) {
                     error!("failed to retry request: {}", e)
                 }
             }
             Err(HttpError::Timeout(body)) => {
                 Metrics::http().add_request_timeout(start);
                 warn!("failed sending http request, retrying: request timed out!");
-                if let Err(e) = retry.retry(file_offsets, &body) {
+                if let Err(e) = self.retry.retry_attempt(file_offsets, &body, attempt + 1) {
                     error!("failed to retry request: {}", e)
                 };
             }
@@ -102,6 +129,7 @@ impl Client {
             }
             Ok(Response::Sent) => {
                 Metrics::http().add_request_success(start);
+                self.limiter.on_success().await;
                 if let Some(sf) = sf {
                     // Flush the state
                     if let Err(e) = sf.flush().await {
@@ -0,0 +1,220 @@
+//! Concurrency limiter for in-flight ingest requests.
+//!
+//! Starts at a configured concurrency and adapts to server backpressure: a
+//! 429/503 response halves the permit count (and, if the response carries a
+//! `Retry-After` header, pauses handing out new slots until that deadline);
+//! sustained success slowly ramps the permit count back up toward the
+//! configured max. This is a standard additive-increase/multiplicative-decrease
+//! scheme, the same shape TCP congestion control uses.
+//!
+//! The underlying semaphore is always sized to `max` so permits already
+//! checked out by in-flight requests are never forcibly revoked; instead,
+//! `target` is how many of those permits may be in use at once (a new slot
+//! is only acquired while fewer than `target` are held), and every state transition (throttle, ramp-up) is serialized
+//! under `state` so concurrent 429s can't race each other into
+//! under-applying the multiplicative decrease.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::warn;
+use tokio::sync::{Mutex, Semaphore, SemaphorePermit};
+use tokio::time;
+
+use metrics::Metrics;
+
+/// Starting/min/max bounds for the adaptive limiter, read from env vars.
+#[derive(Clone, Copy)]
+pub struct RateLimiterConfig {
+    pub starting: usize,
+    pub min: usize,
+    pub max: usize,
+}
+
+impl RateLimiterConfig {
+    pub fn from_env() -> Self {
+        use config::env_vars;
+
+        let starting = std::env::var(env_vars::RATE_LIMIT_STARTING)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(10);
+        let min = std::env::var(env_vars::RATE_LIMIT_MIN)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1);
+        let max = std::env::var(env_vars::RATE_LIMIT_MAX)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(starting.max(10));
+
+        Self::clamped(starting, min, max)
+    }
+
+    /// Builds a config from explicit bounds, clamping `min` down to `max`
+    /// (and warning) if the two are out of order, so a misconfigured floor
+    /// above the ceiling can't leave the limiter permanently unable to
+    /// satisfy both bounds at once.
+    fn clamped(starting: usize, min: usize, max: usize) -> Self {
+        if min > max {
+            warn!(
+                "{} ({}) is greater than {} ({}), ignoring and using {} for both",
+                config::env_vars::RATE_LIMIT_MIN,
+                min,
+                config::env_vars::RATE_LIMIT_MAX,
+                max,
+                max
+            );
+            return Self {
+                starting: max,
+                min: max,
+                max,
+            };
+        }
+
+        Self { starting, min, max }
+    }
+}
+
+/// Mutable limiter state, updated under a single lock so throttle/ramp-up
+/// transitions apply in order rather than racing on a bare load-then-store.
+struct State {
+    target: usize,
+    paused_until: Option<Instant>,
+}
+
+struct Inner {
+    /// Always sized to `config.max`; `state.target` governs how many of
+    /// those permits are currently allowed to be handed out.
+    semaphore: Semaphore,
+    state: Mutex<State>,
+}
+
+/// Hands out slots for in-flight requests, shrinking and growing the
+/// effective concurrency in response to server backpressure.
+pub struct RateLimiter {
+    config: RateLimiterConfig,
+    inner: Arc<Inner>,
+}
+
+impl RateLimiter {
+    pub fn new(starting: usize) -> Self {
+        Self::with_config(RateLimiterConfig {
+            starting,
+            min: 1,
+            max: starting.max(1),
+        })
+    }
+
+    pub fn with_config(config: RateLimiterConfig) -> Self {
+        let target = config.starting.max(config.min).min(config.max);
+        Metrics::http().set_rate_limit(target as u64);
+        Self {
+            config,
+            inner: Arc::new(Inner {
+                semaphore: Semaphore::new(config.max),
+                state: Mutex::new(State {
+                    target,
+                    paused_until: None,
+                }),
+            }),
+        }
+    }
+
+    /// Acquires a slot for `body`, waiting out any active backpressure pause
+    /// and the current target concurrency first. Returns the body back to
+    /// the caller once a slot is held, so the caller can hand it to the
+    /// transport while the guard is alive.
+    pub async fn get_slot<'a, T>(&'a self, body: T) -> Guarded<'a, T> {
+        loop {
+            let wait = {
+                let state = self.inner.state.lock().await;
+                state
+                    .paused_until
+                    .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+            };
+            match wait {
+                Some(d) if !d.is_zero() => time::sleep(d).await,
+                _ => break,
+            }
+        }
+
+        // Only ever let `target` of the `max`-sized semaphore's permits be
+        // in use at once, so a shrink takes effect for the next acquirer
+        // immediately even though currently in-flight permits aren't
+        // forcibly revoked.
+        loop {
+            let target = self.inner.state.lock().await.target;
+            let in_use = self.config.max - self.inner.semaphore.available_permits();
+            if in_use < target {
+                break;
+            }
+            time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let permit = self
+            .inner
+            .semaphore
+            .acquire()
+            .await
+            .expect("rate limiter semaphore closed");
+        Guarded { body, _permit: permit }
+    }
+
+    /// Called after the ingest API returns 429/503: halves the permit count
+    /// (down to `min`) and, if the response carried a `Retry-After` value,
+    /// pauses new acquisitions until that deadline.
+    pub async fn on_throttled(&self, retry_after: Option<Duration>) {
+        let mut state = self.inner.state.lock().await;
+        state.target = (state.target / 2).max(self.config.min);
+        if let Some(delay) = retry_after {
+            state.paused_until = Some(Instant::now() + delay);
+        }
+        Metrics::http().set_rate_limit(state.target as u64);
+    }
+
+    /// Called after a successful send: slowly ramps the permit count back up
+    /// by one, up to `max`.
+    pub async fn on_success(&self) {
+        let mut state = self.inner.state.lock().await;
+        if state.target < self.config.max {
+            state.target += 1;
+            Metrics::http().set_rate_limit(state.target as u64);
+        }
+    }
+}
+
+/// A value held alongside the permit that authorized sending it; dropping
+/// this releases the slot.
+pub struct Guarded<'a, T> {
+    body: T,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl<'a, T> std::ops::Deref for Guarded<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.body
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiterConfig;
+
+    #[test]
+    fn clamps_min_down_to_max_when_min_exceeds_max() {
+        let config = RateLimiterConfig::clamped(5, 20, 10);
+        assert_eq!(config.max, 10);
+        assert_eq!(config.min, 10);
+        assert_eq!(config.starting, 10);
+    }
+
+    #[test]
+    fn leaves_well_formed_bounds_untouched() {
+        let config = RateLimiterConfig::clamped(5, 1, 10);
+        assert_eq!(config.starting, 5);
+        assert_eq!(config.min, 1);
+        assert_eq!(config.max, 10);
+    }
+}
@@ -0,0 +1,303 @@
+//! Disk-backed retry queue for failed/timed-out ingest requests.
+//!
+//! Delay between attempts follows full-jitter exponential backoff:
+//! `delay = random(0, min(base * 2^attempt, max_delay))`. An item that's been
+//! retried `max_attempts` times is moved to the dead-letter area instead of
+//! being requeued forever, and a "tranquility" throttle paces how fast the
+//! background poller drains the queue so a flood of queued retries doesn't
+//! saturate the link the moment the ingest endpoint recovers.
+//!
+//! Each queued request is two files sharing a UUID stem: a `.body` file
+//! holding the raw request bytes, and a `.meta.json` sidecar holding the
+//! file offsets, the attempt count so far, and when the record becomes due.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::time;
+
+use metrics::Metrics;
+
+use crate::types::body::IngestBodyBuffer;
+use crate::Offset;
+
+quick_error::quick_error! {
+    #[derive(Debug)]
+    pub enum RetryError {
+        Io(source: std::io::Error) {
+            from()
+            display("retry queue io error: {}", source)
+        }
+        Serialize(source: serde_json::Error) {
+            from()
+            display("failed to serialize retry record: {}", source)
+        }
+    }
+}
+
+/// Tuning knobs for the retry policy, read from env vars at startup.
+#[derive(Clone, Copy)]
+pub struct RetryConfig {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub max_attempts: u32,
+    /// Minimum spacing enforced between successive drains of the retry
+    /// queue, so reconnection doesn't look like a retry storm.
+    pub throttle: Duration,
+}
+
+impl RetryConfig {
+    pub fn from_env(base_delay: Duration) -> Self {
+        use config::env_vars;
+
+        let max_delay = std::env::var(env_vars::RETRY_MAX_DELAY)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(300));
+        let max_attempts = std::env::var(env_vars::RETRY_MAX_ATTEMPTS)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(20);
+        let throttle = std::env::var(env_vars::RETRY_THROTTLE)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_millis)
+            .unwrap_or(Duration::from_millis(250));
+
+        Self {
+            base_delay,
+            max_delay,
+            max_attempts,
+            throttle,
+        }
+    }
+}
+
+/// The on-disk sidecar for a queued retry record.
+#[derive(Serialize, Deserialize)]
+struct RetryMeta {
+    offsets: Option<Vec<Offset>>,
+    attempt: u32,
+    ready_at_unix_ms: u64,
+}
+
+/// Queues failed/timed-out requests to disk and hands them back out on a
+/// jittered exponential backoff, capping how long a single request is
+/// allowed to live before it's dead-lettered.
+pub struct Retry {
+    config: RetryConfig,
+    dir: PathBuf,
+    dead_letter_dir: PathBuf,
+    /// When the queue last handed a record back out, so `poll` can pace
+    /// successive drains without blocking callers when the queue is empty.
+    last_drain: Mutex<Option<Instant>>,
+}
+
+impl Retry {
+    pub fn new(config: RetryConfig, dir: PathBuf, dead_letter_dir: PathBuf) -> Self {
+        Self {
+            config,
+            dir,
+            dead_letter_dir,
+            last_drain: Mutex::new(None),
+        }
+    }
+
+    /// Computes the jittered backoff delay for the given attempt count:
+    /// `random(0, min(base * 2^attempt, max_delay))`.
+    fn delay_for(&self, attempt: u32) -> Duration {
+        let exp = self
+            .config
+            .base_delay
+            .saturating_mul(1u32.wrapping_shl(attempt.min(31)));
+        let capped = exp.min(self.config.max_delay);
+        let jittered_millis = rand::thread_rng().gen_range(0..=capped.as_millis().max(1) as u64);
+        Duration::from_millis(jittered_millis)
+    }
+
+    /// Queues `body` for retry as a brand-new failure (attempt 0).
+    pub async fn retry(
+        &self,
+        offsets: Option<&[Offset]>,
+        body: &IngestBodyBuffer,
+    ) -> Result<(), RetryError> {
+        self.retry_attempt(offsets, body, 0)
+    }
+
+    /// Queues `body` for retry, recording that it's already been attempted
+    /// `attempt` times. If `attempt` has reached `max_attempts`, the record
+    /// is moved to the dead-letter area instead and a metric is incremented.
+    pub(crate) fn retry_attempt(
+        &self,
+        offsets: Option<&[Offset]>,
+        body: &IngestBodyBuffer,
+        attempt: u32,
+    ) -> Result<(), RetryError> {
+        if attempt >= self.config.max_attempts {
+            warn!(
+                "request exceeded {} retry attempts, moving to dead-letter",
+                self.config.max_attempts
+            );
+            Metrics::http().add_retry_dead_lettered();
+            return self.write_record(&self.dead_letter_dir, offsets, body, attempt, None);
+        }
+
+        let delay = self.delay_for(attempt);
+        Metrics::http().add_retry_queued();
+        self.write_record(&self.dir, offsets, body, attempt, Some(delay))
+    }
+
+    fn write_record(
+        &self,
+        dir: &Path,
+        offsets: Option<&[Offset]>,
+        body: &IngestBodyBuffer,
+        attempt: u32,
+        delay: Option<Duration>,
+    ) -> Result<(), RetryError> {
+        std::fs::create_dir_all(dir)?;
+        let ready_at_unix_ms = now_unix_ms() + delay.unwrap_or_default().as_millis() as u64;
+        let meta = RetryMeta {
+            offsets: offsets.map(|o| o.to_vec()),
+            attempt,
+            ready_at_unix_ms,
+        };
+
+        let stem = uuid::Uuid::new_v4().to_string();
+        std::fs::write(dir.join(format!("{}.body", stem)), body.as_bytes())?;
+        std::fs::write(
+            dir.join(format!("{}.meta.json", stem)),
+            serde_json::to_vec(&meta)?,
+        )?;
+        Ok(())
+    }
+
+    /// Pops the earliest due record off the queue (if any) and returns it
+    /// along with its attempt count, so the caller can requeue with an
+    /// incremented attempt if resending it fails again. Returns immediately
+    /// when the queue is empty; only paces *actual* drains apart by the
+    /// configured throttle, so a normal send that finds nothing queued never
+    /// blocks on it.
+    pub async fn poll(
+        &self,
+    ) -> Result<(Option<Vec<Offset>>, Option<(IngestBodyBuffer, u32)>), RetryError> {
+        let Some(due) = self.find_due_record()? else {
+            return Ok((None, None));
+        };
+
+        {
+            let mut last_drain = self.last_drain.lock().await;
+            if let Some(last) = *last_drain {
+                let elapsed = last.elapsed();
+                if elapsed < self.config.throttle {
+                    time::sleep(self.config.throttle - elapsed).await;
+                }
+            }
+            *last_drain = Some(Instant::now());
+        }
+
+        let body_path = self.dir.join(format!("{}.body", due.stem));
+        let bytes = std::fs::read(&body_path)?;
+        std::fs::remove_file(&body_path)?;
+        std::fs::remove_file(self.dir.join(format!("{}.meta.json", due.stem)))?;
+
+        Ok((due.meta.offsets, Some((IngestBodyBuffer::from(bytes), due.meta.attempt))))
+    }
+
+    fn find_due_record(&self) -> Result<Option<DueRecord>, RetryError> {
+        let now = now_unix_ms();
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut earliest: Option<DueRecord> = None;
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(stem) = path.file_name().and_then(|n| n.to_str()).map(|n| {
+                n.trim_end_matches(".meta.json").to_string()
+            }) else {
+                continue;
+            };
+
+            let meta: RetryMeta = match std::fs::read(&path).map(|b| serde_json::from_slice(&b)) {
+                Ok(Ok(meta)) => meta,
+                _ => continue,
+            };
+            if meta.ready_at_unix_ms > now {
+                continue;
+            }
+            if earliest
+                .as_ref()
+                .map(|e| meta.ready_at_unix_ms < e.meta.ready_at_unix_ms)
+                .unwrap_or(true)
+            {
+                earliest = Some(DueRecord { stem, meta });
+            }
+        }
+
+        Ok(earliest)
+    }
+}
+
+struct DueRecord {
+    stem: String,
+    meta: RetryMeta,
+}
+
+fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retry(max_delay: Duration) -> Retry {
+        Retry::new(
+            RetryConfig {
+                base_delay: Duration::from_millis(100),
+                max_delay,
+                max_attempts: 20,
+                throttle: Duration::from_millis(0),
+            },
+            PathBuf::from("/tmp/mz-retry-test"),
+            PathBuf::from("/tmp/mz-retry-test-dead-letter"),
+        )
+    }
+
+    #[test]
+    fn delay_never_exceeds_max_delay() {
+        let retry = retry(Duration::from_secs(1));
+        for attempt in 0..40 {
+            assert!(retry.delay_for(attempt) <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn delay_for_zero_is_bounded_by_base_delay() {
+        let retry = retry(Duration::from_secs(300));
+        assert!(retry.delay_for(0) <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn delay_for_large_attempt_is_clamped_to_max_delay() {
+        let retry = retry(Duration::from_secs(300));
+        // base_delay * 2^20 (about 29 hours) is already far past max_delay, so
+        // this attempt's upper bound should be clamped to max_delay rather
+        // than overflowing.
+        assert!(retry.delay_for(20) <= Duration::from_secs(300));
+    }
+}
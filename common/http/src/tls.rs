@@ -0,0 +1,204 @@
+//! TLS trust root and client identity configuration for the ingest client.
+//!
+//! By default the client trusts the bundled `webpki-roots`. This module lets
+//! a deployment behind a private CA, or one that requires mutual TLS to
+//! reach the ingest endpoint, extend that trust; the bundled roots are always
+//! kept.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+quick_error::quick_error! {
+    #[derive(Debug)]
+    pub enum TlsConfigError {
+        ReadFile(path: PathBuf, source: std::io::Error) {
+            display("failed to read {:?}: {}", path, source)
+        }
+        ParseCert(path: PathBuf, source: std::io::Error) {
+            display("{:?} does not contain a valid PEM certificate: {}", path, source)
+        }
+        ParseKey(path: PathBuf) {
+            display("{:?} does not contain a valid PEM private key", path)
+        }
+        ClientIdentityIncomplete {
+            display("MZ_TLS_CLIENT_CERT and MZ_TLS_CLIENT_KEY must both be set, or neither")
+        }
+        BuildRoots(source: rustls::Error) {
+            display("failed to build TLS client config: {}", source)
+        }
+    }
+}
+
+/// A client certificate/key pair presented for mutual TLS.
+#[derive(Clone)]
+pub struct ClientIdentity {
+    pub cert_chain: Vec<rustls::Certificate>,
+    pub key: rustls::PrivateKey,
+}
+
+/// Resolved TLS configuration for the ingest client, built once at startup so
+/// bad paths or malformed PEM data fail fast instead of surfacing as opaque
+/// connection errors when the first request is sent.
+#[derive(Clone, Default)]
+pub struct TlsConfig {
+    /// Trust the OS's native certificate store in addition to the bundled
+    /// roots.
+    pub use_native_roots: bool,
+    /// Extra PEM-encoded CA bundle to trust, e.g. a private CA.
+    pub extra_ca_certs: Option<PathBuf>,
+    /// Client identity presented for mutual TLS, if configured.
+    pub client_identity: Option<ClientIdentity>,
+}
+
+impl TlsConfig {
+    /// Validates all configured paths up front and loads the client identity
+    /// (if any), so misconfiguration is reported at startup.
+    pub fn from_env() -> Result<Self, TlsConfigError> {
+        use config::env_vars;
+
+        let use_native_roots = std::env::var_os(env_vars::TLS_USE_NATIVE_ROOTS).is_some();
+        let extra_ca_certs = std::env::var_os(env_vars::TLS_CA_CERTS).map(PathBuf::from);
+        if let Some(path) = &extra_ca_certs {
+            load_certs(path)?;
+        }
+
+        let cert_path = std::env::var_os(env_vars::TLS_CLIENT_CERT).map(PathBuf::from);
+        let key_path = std::env::var_os(env_vars::TLS_CLIENT_KEY).map(PathBuf::from);
+        let client_identity = match (cert_path, key_path) {
+            (Some(cert_path), Some(key_path)) => Some(ClientIdentity {
+                cert_chain: load_certs(&cert_path)?,
+                key: load_key(&key_path)?,
+            }),
+            (None, None) => None,
+            _ => return Err(TlsConfigError::ClientIdentityIncomplete),
+        };
+
+        Ok(Self {
+            use_native_roots,
+            extra_ca_certs,
+            client_identity,
+        })
+    }
+
+    /// Builds the rustls root store implied by this config: the bundled
+    /// webpki roots, plus the OS native store if requested, plus any extra
+    /// CA bundle.
+    pub fn root_store(&self) -> Result<rustls::RootCertStore, TlsConfigError> {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+            rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+                ta.subject,
+                ta.spki,
+                ta.name_constraints,
+            )
+        }));
+
+        if self.use_native_roots {
+            for cert in rustls_native_certs::load_native_certs()
+                .map_err(|e| TlsConfigError::ReadFile(PathBuf::from("<native store>"), e))?
+            {
+                // Certs that the OS store accepted but rustls rejects are
+                // skipped rather than failing startup outright.
+                let _ = roots.add(&rustls::Certificate(cert.0));
+            }
+        }
+
+        if let Some(path) = &self.extra_ca_certs {
+            for cert in load_certs(path)? {
+                roots
+                    .add(&cert)
+                    .map_err(TlsConfigError::BuildRoots)?;
+            }
+        }
+
+        Ok(roots)
+    }
+
+    /// Builds the actual `rustls::ClientConfig` the ingest connection should
+    /// use: the trust roots from [`TlsConfig::root_store`], presenting
+    /// [`TlsConfig::client_identity`] for mutual TLS if one was configured.
+    pub fn client_config(&self) -> Result<rustls::ClientConfig, TlsConfigError> {
+        let roots = self.root_store()?;
+        let builder = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots);
+
+        let config = match &self.client_identity {
+            Some(identity) => builder
+                .with_client_auth_cert(identity.cert_chain.clone(), identity.key.clone())
+                .map_err(TlsConfigError::BuildRoots)?,
+            None => builder.with_no_client_auth(),
+        };
+
+        Ok(config)
+    }
+}
+
+fn load_certs(path: &Path) -> Result<Vec<rustls::Certificate>, TlsConfigError> {
+    let pem = fs::read(path).map_err(|e| TlsConfigError::ReadFile(path.to_path_buf(), e))?;
+    let certs = rustls_pemfile::certs(&mut pem.as_slice())
+        .map_err(|e| TlsConfigError::ParseCert(path.to_path_buf(), e))?;
+    if certs.is_empty() {
+        return Err(TlsConfigError::ParseCert(
+            path.to_path_buf(),
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "no certificates found"),
+        ));
+    }
+    Ok(certs.into_iter().map(rustls::Certificate).collect())
+}
+
+fn load_key(path: &Path) -> Result<rustls::PrivateKey, TlsConfigError> {
+    let pem = fs::read(path).map_err(|e| TlsConfigError::ReadFile(path.to_path_buf(), e))?;
+    let keys = rustls_pemfile::pkcs8_private_keys(&mut pem.as_slice())
+        .map_err(|_| TlsConfigError::ParseKey(path.to_path_buf()))?;
+    keys.into_iter()
+        .next()
+        .map(rustls::PrivateKey)
+        .ok_or_else(|| TlsConfigError::ParseKey(path.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // MZ_TLS_* env vars are process-global, so serialize tests that touch them.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn errors_when_only_client_cert_is_set() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        std::env::remove_var(config::env_vars::TLS_CA_CERTS);
+        std::env::remove_var(config::env_vars::TLS_CLIENT_KEY);
+        std::env::set_var(config::env_vars::TLS_CLIENT_CERT, "/tmp/does-not-matter.pem");
+
+        let result = TlsConfig::from_env();
+
+        std::env::remove_var(config::env_vars::TLS_CLIENT_CERT);
+        assert!(matches!(result, Err(TlsConfigError::ClientIdentityIncomplete)));
+    }
+
+    #[test]
+    fn errors_when_only_client_key_is_set() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        std::env::remove_var(config::env_vars::TLS_CA_CERTS);
+        std::env::remove_var(config::env_vars::TLS_CLIENT_CERT);
+        std::env::set_var(config::env_vars::TLS_CLIENT_KEY, "/tmp/does-not-matter.key");
+
+        let result = TlsConfig::from_env();
+
+        std::env::remove_var(config::env_vars::TLS_CLIENT_KEY);
+        assert!(matches!(result, Err(TlsConfigError::ClientIdentityIncomplete)));
+    }
+
+    #[test]
+    fn no_client_identity_env_vars_is_fine() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        std::env::remove_var(config::env_vars::TLS_CA_CERTS);
+        std::env::remove_var(config::env_vars::TLS_CLIENT_CERT);
+        std::env::remove_var(config::env_vars::TLS_CLIENT_KEY);
+
+        let config = TlsConfig::from_env().expect("no identity configured should not error");
+        assert!(config.client_identity.is_none());
+    }
+}
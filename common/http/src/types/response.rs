@@ -0,0 +1,17 @@
+//! The outcome of sending one request to the ingest endpoint.
+
+use std::time::Duration;
+
+use crate::types::body::IngestBodyBuffer;
+
+/// Result of a completed (not errored-out) request to the ingest endpoint.
+#[derive(Debug)]
+pub enum Response {
+    /// Accepted by the ingest endpoint.
+    Sent,
+    /// Rejected by the ingest endpoint: the body that was sent, the HTTP
+    /// status code, a human-readable reason, and -- for 429/503 -- the
+    /// `Retry-After` delay the response carried, if any, so the caller can
+    /// pause new sends until that deadline.
+    Failed(Option<IngestBodyBuffer>, u16, String, Option<Duration>),
+}
@@ -0,0 +1,193 @@
+//! Process-wide metrics registry, exposed on the Prometheus endpoint at
+//! `MZ_METRICS_PORT`.
+//!
+//! `Metrics::http()` and `Metrics::system()` hand back handles to the two
+//! gauge/counter groups the agent currently publishes: HTTP ingest request
+//! outcomes, and the agent's own self-telemetry.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use once_cell::sync::Lazy;
+use prometheus::{Gauge, IntCounter, IntGauge};
+
+/// Registers `metric` with the default Prometheus registry so it's served
+/// on `/metrics`, then hands it back. Registration only fails on a name
+/// collision, which would be a programming error here since every metric
+/// name below is declared exactly once.
+fn register<T: prometheus::core::Collector + Clone + 'static>(metric: T) -> T {
+    prometheus::register(Box::new(metric.clone())).expect("metric name collision");
+    metric
+}
+
+pub struct Metrics;
+
+impl Metrics {
+    pub fn http() -> &'static HttpMetrics {
+        &HTTP_METRICS
+    }
+
+    pub fn system() -> &'static SystemMetrics {
+        &SYSTEM_METRICS
+    }
+}
+
+/// HTTP ingest request outcome counters/gauges.
+pub struct HttpMetrics {
+    request_size: IntCounter,
+    request_success: IntCounter,
+    request_failure: IntCounter,
+    request_timeout: IntCounter,
+    retry_queued: IntCounter,
+    retry_dead_lettered: IntCounter,
+    rate_limit: IntGauge,
+}
+
+impl HttpMetrics {
+    fn new() -> Self {
+        Self {
+            request_size: register(
+                IntCounter::new(
+                    "ingest_request_bytes_total",
+                    "Total bytes sent to the ingest endpoint",
+                )
+                .unwrap(),
+            ),
+            request_success: register(
+                IntCounter::new(
+                    "ingest_request_success_total",
+                    "Total successful ingest requests",
+                )
+                .unwrap(),
+            ),
+            request_failure: register(
+                IntCounter::new(
+                    "ingest_request_failure_total",
+                    "Total failed ingest requests",
+                )
+                .unwrap(),
+            ),
+            request_timeout: register(
+                IntCounter::new(
+                    "ingest_request_timeout_total",
+                    "Total timed out ingest requests",
+                )
+                .unwrap(),
+            ),
+            retry_queued: register(
+                IntCounter::new(
+                    "ingest_retry_queued_total",
+                    "Total requests queued for retry",
+                )
+                .unwrap(),
+            ),
+            retry_dead_lettered: register(
+                IntCounter::new(
+                    "ingest_retry_dead_lettered_total",
+                    "Total requests moved to the dead-letter area after exceeding max attempts",
+                )
+                .unwrap(),
+            ),
+            rate_limit: register(
+                IntGauge::new(
+                    "ingest_rate_limit_current",
+                    "Current effective concurrency limit for ingest requests",
+                )
+                .unwrap(),
+            ),
+        }
+    }
+
+    pub fn add_request_size(&self, bytes: u64) {
+        self.request_size.inc_by(bytes);
+    }
+
+    pub fn add_request_success(&self, _start: std::time::Instant) {
+        self.request_success.inc();
+    }
+
+    pub fn add_request_failure(&self, _start: std::time::Instant) {
+        self.request_failure.inc();
+    }
+
+    pub fn add_request_timeout(&self, _start: std::time::Instant) {
+        self.request_timeout.inc();
+    }
+
+    pub fn add_retry_queued(&self) {
+        self.retry_queued.inc();
+    }
+
+    pub fn add_retry_dead_lettered(&self) {
+        self.retry_dead_lettered.inc();
+    }
+
+    pub fn set_rate_limit(&self, limit: u64) {
+        self.rate_limit.set(limit as i64);
+    }
+}
+
+/// Agent self-telemetry gauges: resident memory, cpu percent, open file
+/// descriptors, thread count, and uptime.
+pub struct SystemMetrics {
+    resident_memory_bytes: IntGauge,
+    cpu_percent: Gauge,
+    open_fds: IntGauge,
+    thread_count: IntGauge,
+    uptime_seconds: AtomicU64,
+    uptime_gauge: IntGauge,
+}
+
+impl SystemMetrics {
+    fn new() -> Self {
+        Self {
+            resident_memory_bytes: register(
+                IntGauge::new(
+                    "agent_resident_memory_bytes",
+                    "Resident memory used by the agent process",
+                )
+                .unwrap(),
+            ),
+            cpu_percent: register(
+                Gauge::new("agent_cpu_percent", "CPU percent used by the agent process").unwrap(),
+            ),
+            open_fds: register(
+                IntGauge::new(
+                    "agent_open_file_descriptors",
+                    "Open file descriptors held by the agent process",
+                )
+                .unwrap(),
+            ),
+            thread_count: register(
+                IntGauge::new("agent_thread_count", "Threads held by the agent process").unwrap(),
+            ),
+            uptime_seconds: AtomicU64::new(0),
+            uptime_gauge: register(
+                IntGauge::new("agent_uptime_seconds", "Agent process uptime").unwrap(),
+            ),
+        }
+    }
+
+    pub fn set_resident_memory_bytes(&self, bytes: u64) {
+        self.resident_memory_bytes.set(bytes as i64);
+    }
+
+    pub fn set_cpu_percent(&self, percent: f32) {
+        self.cpu_percent.set(percent as f64);
+    }
+
+    pub fn set_open_fds(&self, count: u64) {
+        self.open_fds.set(count as i64);
+    }
+
+    pub fn set_thread_count(&self, count: u64) {
+        self.thread_count.set(count as i64);
+    }
+
+    pub fn set_uptime_seconds(&self, uptime: u64) {
+        self.uptime_seconds.store(uptime, Ordering::Relaxed);
+        self.uptime_gauge.set(uptime as i64);
+    }
+}
+
+static HTTP_METRICS: Lazy<HttpMetrics> = Lazy::new(HttpMetrics::new);
+static SYSTEM_METRICS: Lazy<SystemMetrics> = Lazy::new(SystemMetrics::new);
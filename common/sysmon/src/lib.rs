@@ -0,0 +1,112 @@
+//! Periodic self-telemetry: samples the agent's own process and registers
+//! the results as gauges on the metrics endpoint, alongside the existing
+//! HTTP request metrics.
+//!
+//! A tailing agent holds onto a lot of inotify/file handles, so the open
+//! file-descriptor count in particular is worth alerting on before the
+//! process hits its ulimit.
+
+use std::time::Duration;
+
+use log::{info, warn};
+use metrics::Metrics;
+use sysinfo::{Pid, ProcessRefreshKind, System};
+use tokio::time;
+
+/// Samples this process and publishes gauges for it on every tick.
+pub struct SystemMonitor {
+    interval: Duration,
+    log_summary: bool,
+    pid: Pid,
+    system: System,
+}
+
+impl SystemMonitor {
+    pub fn new(interval: Duration, log_summary: bool) -> Self {
+        let pid = Pid::from(std::process::id() as usize);
+        Self {
+            interval,
+            log_summary,
+            pid,
+            system: System::new(),
+        }
+    }
+
+    /// Runs forever, sampling and publishing on `interval`. Spawn this as its
+    /// own task.
+    pub async fn run(mut self) {
+        let mut ticker = time::interval(self.interval);
+        loop {
+            ticker.tick().await;
+            self.sample();
+        }
+    }
+
+    fn sample(&mut self) {
+        self.system.refresh_process_specifics(
+            self.pid,
+            ProcessRefreshKind::new().with_cpu().with_memory(),
+        );
+
+        let Some(process) = self.system.process(self.pid) else {
+            warn!("system monitor could not find its own process ({})", self.pid);
+            return;
+        };
+
+        let resident_bytes = process.memory();
+        let cpu_percent = process.cpu_usage();
+        let uptime_secs = process.run_time();
+        let open_fds = open_fd_count().unwrap_or(0);
+        let thread_count = thread_count().unwrap_or(0);
+
+        let metrics = Metrics::system();
+        metrics.set_resident_memory_bytes(resident_bytes);
+        metrics.set_cpu_percent(cpu_percent);
+        metrics.set_open_fds(open_fds);
+        metrics.set_thread_count(thread_count);
+        metrics.set_uptime_seconds(uptime_secs);
+
+        if self.log_summary {
+            info!(
+                "agent self-telemetry: rss={}B cpu={:.1}% fds={} threads={} uptime={}s",
+                resident_bytes, cpu_percent, open_fds, thread_count, uptime_secs
+            );
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_fd_count() -> std::io::Result<u64> {
+    Ok(std::fs::read_dir("/proc/self/fd")?.count() as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_fd_count() -> std::io::Result<u64> {
+    Ok(0)
+}
+
+#[cfg(target_os = "linux")]
+fn thread_count() -> std::io::Result<u64> {
+    Ok(std::fs::read_dir("/proc/self/task")?.count() as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn thread_count() -> std::io::Result<u64> {
+    Ok(0)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_fd_count_sees_this_process_stdio() {
+        // The test harness itself has stdin/stdout/stderr open at minimum.
+        assert!(open_fd_count().unwrap() >= 3);
+    }
+
+    #[test]
+    fn thread_count_is_at_least_one() {
+        assert!(thread_count().unwrap() >= 1);
+    }
+}
@@ -0,0 +1,9 @@
+//! Generated bindings for the `mz:transform` WIT world (see `wit/transform.wit`):
+//! the `transform-host` import modules get a logging facade through, and the
+//! `transform` interface every module must export.
+
+wasmtime::component::bindgen!({
+    world: "transform-host",
+    path: "wit",
+    async: true,
+});
@@ -0,0 +1,31 @@
+//! Host-side state handed to each wasm instance: a logging facade tagged with
+//! the module's name, with WASI filesystem/network access left disabled. No
+//! module config is passed to the instance.
+
+use log::{debug, error, trace, warn};
+
+use crate::manifest::ModuleManifest;
+
+/// Per-call state given to a module instance. Carries nothing that would let
+/// a module reach outside the sandbox -- no WASI preopens, no sockets.
+pub struct HostState {
+    module_name: String,
+}
+
+impl HostState {
+    pub fn new(manifest: &ModuleManifest) -> Self {
+        Self {
+            module_name: manifest.name.clone(),
+        }
+    }
+
+    /// Implements the `log` import modules call instead of touching stdio.
+    pub fn host_log(&self, level: &str, message: &str) {
+        match level {
+            "error" => error!("[wasm:{}] {}", self.module_name, message),
+            "warn" => warn!("[wasm:{}] {}", self.module_name, message),
+            "debug" => debug!("[wasm:{}] {}", self.module_name, message),
+            _ => trace!("[wasm:{}] {}", self.module_name, message),
+        }
+    }
+}
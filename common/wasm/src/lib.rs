@@ -0,0 +1,174 @@
+//! Message Rewrite Facility: a pluggable, sandboxed log transformation stage.
+//!
+//! Modules are compiled WebAssembly components, compiled once at startup from
+//! the directory pointed to by `config::env_vars::WASM_MODULES` and
+//! instantiated afresh for each line. Each line passes through the configured
+//! modules, in declared order, before it is handed off to the ingestion
+//! pipeline. A module may accept a line unchanged, accept it with
+//! modifications, reject (drop) it, or error -- in which case the line is
+//! passed through unmodified and the failure is logged.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use log::{info, warn};
+
+mod bindings;
+pub mod host;
+pub mod manifest;
+pub mod pool;
+
+use bindings::exports::mz::transform::transform::{Line as WitLine, Outcome as WitOutcome};
+use host::HostState;
+use manifest::ModuleManifest;
+use pool::InstancePool;
+
+/// A line of input as seen by a transform module: the raw message bytes plus
+/// the metadata the agent already carries around for it.
+#[derive(Debug, Clone)]
+pub struct TransformLine {
+    pub line: Vec<u8>,
+    pub timestamp: i64,
+    pub file: String,
+    pub labels: Vec<(String, String)>,
+    pub annotations: Vec<(String, String)>,
+}
+
+impl From<TransformLine> for WitLine {
+    fn from(line: TransformLine) -> Self {
+        WitLine {
+            bytes: line.line,
+            timestamp: line.timestamp,
+            file: line.file,
+            labels: line.labels,
+            annotations: line.annotations,
+        }
+    }
+}
+
+impl From<WitLine> for TransformLine {
+    fn from(line: WitLine) -> Self {
+        TransformLine {
+            line: line.bytes,
+            timestamp: line.timestamp,
+            file: line.file,
+            labels: line.labels,
+            annotations: line.annotations,
+        }
+    }
+}
+
+/// Result of running a line through a single module.
+#[derive(Debug)]
+pub enum TransformOutcome {
+    /// Keep processing with the (possibly modified) line.
+    Accept(TransformLine),
+    /// Drop the line entirely.
+    Reject,
+    /// The module failed; the caller should log it and keep the line as-is.
+    Error(String),
+}
+
+impl From<WitOutcome> for TransformOutcome {
+    fn from(outcome: WitOutcome) -> Self {
+        match outcome {
+            WitOutcome::Accept(line) => TransformOutcome::Accept(line.into()),
+            WitOutcome::Reject => TransformOutcome::Reject,
+            WitOutcome::Error(reason) => TransformOutcome::Error(reason),
+        }
+    }
+}
+
+quick_error::quick_error! {
+    #[derive(Debug)]
+    pub enum WasmError {
+        Load(path: PathBuf, source: anyhow::Error) {
+            display("failed to load wasm module {:?}: {}", path, source)
+        }
+        Manifest(path: PathBuf, reason: String) {
+            display("invalid manifest in {:?}: {}", path, reason)
+        }
+        Instantiate(source: anyhow::Error) {
+            display("failed to instantiate wasm module: {}", source)
+        }
+    }
+}
+
+/// One loaded module: its manifest plus a pool of ready-to-use instances.
+struct LoadedModule {
+    manifest: ModuleManifest,
+    pool: InstancePool,
+}
+
+/// Runs configured modules, in order, over each line before it's enqueued.
+///
+/// Construct once at startup via [`Transforms::load`] and share behind an
+/// `Arc` with the stream adapter. Each module's compiled component is reused,
+/// but every line instantiates it anew; a per-module pool only caps how many
+/// calls run at once.
+pub struct Transforms {
+    modules: Vec<LoadedModule>,
+}
+
+impl Transforms {
+    /// Compile and instantiate every `.wasm` module found directly under
+    /// `dir`, in file-name sort order. Modules that fail to load or whose
+    /// manifest doesn't validate are logged as warnings and skipped rather
+    /// than aborting startup.
+    pub fn load(dir: &Path, pool_size: usize) -> Result<Arc<Self>, WasmError> {
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map_err(|e| WasmError::Load(dir.to_path_buf(), e.into()))?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|ext| ext == "wasm").unwrap_or(false))
+            .collect();
+        entries.sort();
+
+        let mut modules = Vec::with_capacity(entries.len());
+        for path in entries {
+            match Self::load_one(&path, pool_size) {
+                Ok(loaded) => {
+                    info!(
+                        "loaded wasm transform module {:?} ({} v{})",
+                        path, loaded.manifest.name, loaded.manifest.version
+                    );
+                    modules.push(loaded);
+                }
+                Err(e) => warn!("skipping wasm transform module {:?}: {}", path, e),
+            }
+        }
+
+        Ok(Arc::new(Self { modules }))
+    }
+
+    fn load_one(path: &Path, pool_size: usize) -> Result<LoadedModule, WasmError> {
+        let pool = InstancePool::compile(path, pool_size)
+            .map_err(|e| WasmError::Load(path.to_path_buf(), e))?;
+        let manifest = ModuleManifest::from_custom_section(pool.bytes())
+            .map_err(|e| WasmError::Manifest(path.to_path_buf(), e))?;
+        Ok(LoadedModule { manifest, pool })
+    }
+
+    /// Run `line` through every module whose manifest declares it applies to
+    /// `source_tag`, in declared order. Returns `None` if any module rejects
+    /// the line.
+    pub async fn apply(&self, source_tag: &str, mut line: TransformLine) -> Option<TransformLine> {
+        for module in &self.modules {
+            if !module.manifest.applies_to(source_tag) {
+                continue;
+            }
+            let host_state = HostState::new(&module.manifest);
+            match module.pool.transform(host_state, line.clone()).await {
+                TransformOutcome::Accept(updated) => line = updated,
+                TransformOutcome::Reject => return None,
+                TransformOutcome::Error(reason) => {
+                    warn!(
+                        "wasm module {} failed on {:?}, passing line through: {}",
+                        module.manifest.name, line.file, reason
+                    );
+                }
+            }
+        }
+        Some(line)
+    }
+}
@@ -0,0 +1,151 @@
+//! Parsing and validation for the custom WASM section each transform module
+//! must embed describing itself.
+
+use serde::Deserialize;
+
+/// The well-known custom section name modules embed their manifest under.
+pub const MANIFEST_SECTION: &str = "mz-transform-manifest";
+
+/// Module self-description, read out of the module's custom section as JSON.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModuleManifest {
+    pub name: String,
+    /// Semver version of the module, e.g. "1.2.0".
+    pub version: String,
+    /// Log sources/tags this module should run against. An empty list means
+    /// "all sources".
+    #[serde(default)]
+    pub applies_to: Vec<String>,
+    /// JSON schema for the module's config. It is parsed and kept, but the
+    /// agent reads no module config and validates nothing against it.
+    #[serde(default)]
+    pub config_schema: Option<serde_json::Value>,
+}
+
+impl ModuleManifest {
+    /// Extract and validate the manifest from a component binary's custom
+    /// sections. Component-model binaries aren't readable via
+    /// `wasmtime::Module` (that's the core-module encoding), so this walks
+    /// the raw bytes with `wasmparser` instead.
+    pub fn from_custom_section(bytes: &[u8]) -> Result<Self, String> {
+        let section = wasmparser::Parser::new(0)
+            .parse_all(bytes)
+            .filter_map(|payload| payload.ok())
+            .find_map(|payload| match payload {
+                wasmparser::Payload::CustomSection(reader)
+                    if reader.name() == MANIFEST_SECTION =>
+                {
+                    Some(reader.data().to_vec())
+                }
+                _ => None,
+            })
+            .ok_or_else(|| format!("missing {} custom section", MANIFEST_SECTION))?;
+
+        let manifest: ModuleManifest = serde_json::from_slice(&section)
+            .map_err(|e| format!("manifest is not valid JSON: {}", e))?;
+
+        semver::Version::parse(&manifest.version)
+            .map_err(|e| format!("manifest version {:?} is not semver: {}", manifest.version, e))?;
+
+        Ok(manifest)
+    }
+
+    /// Whether this module should run against lines tagged `source_tag`.
+    pub fn applies_to(&self, source_tag: &str) -> bool {
+        self.applies_to.is_empty()
+            || self.applies_to.iter().any(|tag| tag == source_tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a minimal valid wasm binary (empty module body) carrying a
+    /// single custom section named `name` with `payload` as its contents --
+    /// enough for `wasmparser` to walk without needing a real component.
+    fn wasm_with_custom_section(name: &str, payload: &[u8]) -> Vec<u8> {
+        let mut section = Vec::new();
+        leb128(&mut section, name.len() as u64);
+        section.extend_from_slice(name.as_bytes());
+        section.extend_from_slice(payload);
+
+        let mut out = Vec::new();
+        out.extend_from_slice(b"\0asm");
+        out.extend_from_slice(&1u32.to_le_bytes());
+        out.push(0); // custom section id
+        leb128(&mut out, section.len() as u64);
+        out.extend_from_slice(&section);
+        out
+    }
+
+    fn leb128(out: &mut Vec<u8>, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                break;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    fn manifest_json(version: &str, applies_to: &str) -> Vec<u8> {
+        format!(
+            r#"{{"name":"redact","version":"{}","applies_to":{}}}"#,
+            version, applies_to
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn from_custom_section_reads_a_valid_manifest() {
+        let bytes = wasm_with_custom_section(
+            MANIFEST_SECTION,
+            &manifest_json("1.2.0", r#"["apache"]"#),
+        );
+        let manifest = ModuleManifest::from_custom_section(&bytes).unwrap();
+        assert_eq!(manifest.name, "redact");
+        assert_eq!(manifest.version, "1.2.0");
+        assert_eq!(manifest.applies_to, vec!["apache".to_string()]);
+    }
+
+    #[test]
+    fn from_custom_section_errors_when_section_is_missing() {
+        let bytes = wasm_with_custom_section("some-other-section", b"{}");
+        let err = ModuleManifest::from_custom_section(&bytes).unwrap_err();
+        assert!(err.contains(MANIFEST_SECTION));
+    }
+
+    #[test]
+    fn from_custom_section_errors_on_non_semver_version() {
+        let bytes = wasm_with_custom_section(MANIFEST_SECTION, &manifest_json("not-a-version", "[]"));
+        let err = ModuleManifest::from_custom_section(&bytes).unwrap_err();
+        assert!(err.contains("semver"));
+    }
+
+    #[test]
+    fn applies_to_defaults_to_all_sources_when_empty() {
+        let manifest = ModuleManifest {
+            name: "redact".to_string(),
+            version: "1.0.0".to_string(),
+            applies_to: vec![],
+            config_schema: None,
+        };
+        assert!(manifest.applies_to("apache"));
+        assert!(manifest.applies_to("anything"));
+    }
+
+    #[test]
+    fn applies_to_matches_only_declared_tags() {
+        let manifest = ModuleManifest {
+            name: "redact".to_string(),
+            version: "1.0.0".to_string(),
+            applies_to: vec!["apache".to_string(), "nginx".to_string()],
+            config_schema: None,
+        };
+        assert!(manifest.applies_to("nginx"));
+        assert!(!manifest.applies_to("syslog"));
+    }
+}
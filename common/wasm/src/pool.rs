@@ -0,0 +1,130 @@
+//! Per-module execution: the module is compiled once, and a fixed-size
+//! semaphore bounds how many `transform` calls run at once. Instances are not
+//! reused: every call instantiates the component in a fresh store.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+use wasmtime::component::{Component, Linker};
+use wasmtime::{Config, Engine, Store};
+use wasmtime_wasi::preview2::{Table, WasiCtx, WasiCtxBuilder, WasiView};
+
+use crate::bindings::mz::transform::host::Host as TransformHostImports;
+use crate::bindings::TransformHost;
+use crate::host::HostState;
+use crate::{TransformLine, TransformOutcome};
+
+struct SandboxCtx {
+    wasi: WasiCtx,
+    table: Table,
+    host: HostState,
+}
+
+impl WasiView for SandboxCtx {
+    fn table(&self) -> &Table {
+        &self.table
+    }
+    fn table_mut(&mut self) -> &mut Table {
+        &mut self.table
+    }
+    fn ctx(&self) -> &WasiCtx {
+        &self.wasi
+    }
+    fn ctx_mut(&mut self) -> &mut WasiCtx {
+        &mut self.wasi
+    }
+}
+
+/// The host side of the `mz:transform/host` import: the only non-WASI import
+/// a sandboxed module can call out to.
+impl TransformHostImports for SandboxCtx {
+    fn log(&mut self, level: String, message: String) -> wasmtime::Result<()> {
+        self.host.host_log(&level, &message);
+        Ok(())
+    }
+}
+
+/// Compiled module plus a semaphore that caps concurrent `transform` calls
+/// at `pool_size`; each call instantiates its own instance.
+pub struct InstancePool {
+    engine: Engine,
+    component: Component,
+    linker: Linker<SandboxCtx>,
+    manifest_bytes: Arc<Vec<u8>>,
+    permits: Arc<Semaphore>,
+}
+
+impl InstancePool {
+    pub fn compile(path: &Path, pool_size: usize) -> Result<Self, anyhow::Error> {
+        let mut config = Config::new();
+        config.wasm_component_model(true);
+        config.async_support(true);
+        let engine = Engine::new(&config)?;
+
+        // A component-model binary (required for the WIT `transform` entry
+        // point) is a distinct encoding from a core
+        // wasm module -- there is no single byte stream that's both, so
+        // there's no `Module::from_binary` call here alongside this.
+        let bytes = std::fs::read(path)?;
+        let component = Component::from_binary(&engine, &bytes)?;
+
+        let mut linker: Linker<SandboxCtx> = Linker::new(&engine);
+        // Sandboxed: the WASI command interfaces are linked, but each store's
+        // WasiCtx is built with no filesystem preopens and no network access;
+        // the only other import wired in is the host logging facade (the
+        // generated `mz:transform/host` import).
+        wasmtime_wasi::preview2::command::sync::add_to_linker(&mut linker)?;
+        TransformHost::add_to_linker(&mut linker, |ctx: &mut SandboxCtx| ctx)?;
+
+        Ok(Self {
+            engine,
+            component,
+            linker,
+            manifest_bytes: Arc::new(bytes),
+            permits: Arc::new(Semaphore::new(pool_size.max(1))),
+        })
+    }
+
+    /// Raw module bytes, scanned by [`crate::manifest::ModuleManifest`] for
+    /// its custom section -- component-model binaries aren't readable via
+    /// `wasmtime::Module`, so that scan goes through `wasmparser` directly.
+    pub fn bytes(&self) -> &[u8] {
+        &self.manifest_bytes
+    }
+
+    /// Run one `transform(line)` call in a freshly instantiated instance,
+    /// waiting for a free slot if `pool_size` calls are already running.
+    pub async fn transform(&self, host: HostState, line: TransformLine) -> TransformOutcome {
+        let _permit = self.permits.acquire().await.expect("pool semaphore closed");
+
+        let wasi = WasiCtxBuilder::new().build();
+        let mut store = Store::new(
+            &self.engine,
+            SandboxCtx {
+                wasi,
+                table: Table::new(),
+                host,
+            },
+        );
+
+        match self.call_transform(&mut store, line).await {
+            Ok(outcome) => outcome,
+            Err(e) => TransformOutcome::Error(e.to_string()),
+        }
+    }
+
+    async fn call_transform(
+        &self,
+        store: &mut Store<SandboxCtx>,
+        line: TransformLine,
+    ) -> Result<TransformOutcome, anyhow::Error> {
+        let (instance, _) =
+            TransformHost::instantiate_async(&mut *store, &self.component, &self.linker).await?;
+        let outcome = instance
+            .mz_transform_transform()
+            .call_transform(&mut *store, &line.into())
+            .await?;
+        Ok(outcome.into())
+    }
+}